@@ -0,0 +1,247 @@
+/*
+Resolves a sway output to the backend that can actually control its brightness:
+either a DDC/CI-capable external monitor (driven over I2C) or an embedded eDP
+panel (driven through systemd-logind, which has the necessary privileges to
+write to /sys/class/backlight without extra udev rules).
+*/
+
+use std::path::Path;
+use std::time::Duration;
+
+use ddc::Ddc;
+use lazy_static::lazy_static;
+
+use crate::retry::{with_retry, RetryConfig};
+
+lazy_static! {
+    static ref DBUS_SYSTEM: std::sync::Mutex<zbus::blocking::Connection> = std::sync::Mutex::new(zbus::blocking::Connection::system().unwrap());
+}
+
+/// Identifies which control path a given sway output should be driven through.
+pub enum Backend {
+    /// Refers to a `/sys/class/backlight` device, e.g. "intel_backlight". Controlled
+    /// through systemd-logind's SetBrightness, since that's the only thing with
+    /// permission to write to the sysfs brightness file by default.
+    Backlight(String),
+    /// Refers to the I2C device node that carries DDC/CI for an external monitor,
+    /// e.g. "/dev/i2c-7".
+    DdcI2c(String),
+}
+
+impl Backend {
+    /// Resolves the backend for a given sway output name, dispatching on whether
+    /// it looks like an embedded panel (eDP*) or an external DDC/CI monitor.
+    pub fn for_output(output: &str) -> Backend {
+        Backend::try_for_output(output, true).unwrap_or_else(|| panic!("Could not resolve a backend for output: {}", output))
+    }
+
+    /// Like `for_output`, but returns `None` instead of panicking when nothing
+    /// can be resolved, for callers (like `list`) that probe many outputs and
+    /// want to report unsupported ones rather than aborting on the first.
+    ///
+    /// `verbose` controls whether the AMD/Intel detection diagnostics are printed
+    /// to stderr; `list` passes `false` so its table isn't interleaved with a
+    /// detection line per monitor.
+    pub fn try_for_output(output: &str, verbose: bool) -> Option<Backend> {
+        if output.starts_with("eDP") {
+            try_resolve_edp_backlight_device(output).map(Backend::Backlight)
+        } else {
+            try_get_i2c_dev_by_output(output, verbose).map(Backend::DdcI2c)
+        }
+    }
+
+    /// Returns (current, max) brightness, in the same raw units for both backends
+    /// so callers can compute a percentage the same way regardless of backend.
+    ///
+    /// `max_override`, when set, replaces whatever max the device reports, for
+    /// monitors that report a bogus VCP 0x10 maximum. DDC/CI reads are retried
+    /// per `retry`, since I2C transfers intermittently NAK or return short reads.
+    pub fn get_brightness(&self, max_override: Option<u16>, retry: &RetryConfig) -> Result<(u16, u16), String> {
+        let (current, reported_max) = match self {
+            Backend::Backlight(device) => read_edp_brightness(device)?,
+            Backend::DdcI2c(i2c_path) => {
+                // `get_vcp_feature` itself checks the VCP feature reply's opcode against
+                // the one we requested and errors out on mismatch before returning a
+                // `Value` (which carries only the mh/ml/sh/sl bytes, not the opcode) -
+                // a mismatched reply is just another reason for `with_retry` to retry.
+                let value = with_retry(retry, || {
+                    let mut i2c_ddc = ddc_i2c::from_i2c_device(i2c_path).map_err(|err| err.to_string())?;
+                    i2c_ddc.get_vcp_feature(crate::BRIGHTNESS_VCP_CODE).map_err(|err| err.to_string())
+                })?;
+                crate::value_to_current_and_max(value)
+            }
+        };
+        Ok((current, max_override.unwrap_or(reported_max)))
+    }
+
+    /// Like `get_brightness`, but returns `None` on any I/O or DDC/CI failure
+    /// instead of an error message, and makes a single attempt without retrying,
+    /// for callers that probe many devices (`list`).
+    pub fn try_get_brightness(&self, max_override: Option<u16>) -> Option<(u16, u16)> {
+        let (current, reported_max) = match self {
+            Backend::Backlight(device) => try_read_edp_brightness(device)?,
+            Backend::DdcI2c(i2c_path) => {
+                let mut i2c_ddc = ddc_i2c::from_i2c_device(i2c_path).ok()?;
+                crate::value_to_current_and_max(i2c_ddc.get_vcp_feature(crate::BRIGHTNESS_VCP_CODE).ok()?)
+            }
+        };
+        Some((current, max_override.unwrap_or(reported_max)))
+    }
+
+    /// Stable identifier for this device, used as the key into per-device config
+    /// (e.g. "i2c-7" or "intel_backlight").
+    pub fn device_key(&self) -> String {
+        match self {
+            Backend::Backlight(device) => device.clone(),
+            Backend::DdcI2c(i2c_path) => Path::new(i2c_path).file_name().unwrap().to_string_lossy().to_string(),
+        }
+    }
+
+    /// The I2C device path, for commands that only make sense against a DDC/CI
+    /// monitor (generic VCP access, capabilities). `None` for eDP panels.
+    pub fn i2c_path(&self) -> Option<&str> {
+        match self {
+            Backend::Backlight(_) => None,
+            Backend::DdcI2c(i2c_path) => Some(i2c_path),
+        }
+    }
+
+    /// Writes an absolute brightness value, in the same units returned by `get_brightness`.
+    /// DDC/CI writes are retried per `retry`, for the same reasons as `get_brightness`.
+    pub fn set_brightness(&self, value: u16, retry: &RetryConfig) -> Result<(), String> {
+        match self {
+            Backend::Backlight(device) => set_edp_brightness(device, value),
+            Backend::DdcI2c(i2c_path) => with_retry(retry, || {
+                let mut i2c_ddc = ddc_i2c::from_i2c_device(i2c_path).map_err(|err| err.to_string())?;
+                i2c_ddc.set_vcp_feature(crate::BRIGHTNESS_VCP_CODE, value).map_err(|err| err.to_string())
+            }),
+        }
+    }
+
+    /// Smoothly ramps brightness from `from` to `to` over `duration`, writing
+    /// `steps` evenly-spaced intermediate values instead of jumping straight to
+    /// the target. Works the same way for both backends, since it's built on
+    /// top of `set_brightness`.
+    pub fn ramp_brightness(&self, from: u16, to: u16, duration: Duration, steps: u32, retry: &RetryConfig) -> Result<(), String> {
+        let steps = steps.max(1);
+        let step_delay = duration / steps;
+        for step in 1..=steps {
+            let fraction = step as f32 / steps as f32;
+            let value = (from as f32 + (to as f32 - from as f32) * fraction).round() as u16;
+            self.set_brightness(value, retry)?;
+            if step < steps {
+                std::thread::sleep(step_delay);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the `/sys/class/backlight/<device>` name backing an eDP output, either via
+/// the DRM connector's `intel_backlight` subdirectory or by falling back to the first
+/// (and usually only) entry under `/sys/class/backlight`. Returns `None` if nothing
+/// is found, rather than panicking.
+fn try_resolve_edp_backlight_device(output: &str) -> Option<String> {
+    let drm_backlight = Path::new("/sys/class/drm/").read_dir().ok()?
+        .find(|d| d.as_ref().unwrap().path().to_str().unwrap().ends_with(output))
+        .map(|d| d.unwrap().path().join("intel_backlight"));
+
+    if let Some(path) = drm_backlight {
+        if path.exists() {
+            return Some(path.file_name().unwrap().to_string_lossy().to_string());
+        }
+    }
+
+    // Fallback: scan /sys/class/backlight directly, e.g. for non-Intel panels.
+    let backlight_dir = Path::new("/sys/class/backlight");
+    let device = backlight_dir.read_dir().ok()?.find_map(|d| d.ok())?;
+
+    Some(device.file_name().to_string_lossy().to_string())
+}
+
+fn read_edp_brightness(backlight_device: &str) -> Result<(u16, u16), String> {
+    try_read_edp_brightness(backlight_device)
+        .ok_or_else(|| format!("Failed to read backlight brightness for device: {}", backlight_device))
+}
+
+fn try_read_edp_brightness(backlight_device: &str) -> Option<(u16, u16)> {
+    let base = Path::new("/sys/class/backlight").join(backlight_device);
+    let current = std::fs::read_to_string(base.join("brightness")).ok()?.trim().parse::<u16>().ok()?;
+    let max = std::fs::read_to_string(base.join("max_brightness")).ok()?.trim().parse::<u16>().ok()?;
+    Some((current, max))
+}
+
+fn set_edp_brightness(backlight_device: &str, value: u16) -> Result<(), String> {
+    // systemd-logind gives a function to set brightness level for backlight devices.
+    // This doesn't require extra authentication or filesystem ACL for /sys/class/backlight
+    // devices, as the high-privileged systemd-logind daemon can do that.
+    let dbus_system = DBUS_SYSTEM.lock().unwrap();
+    dbus_system.call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1/session/auto",
+        Some("org.freedesktop.login1.Session"),
+        "SetBrightness",
+        &("backlight", backlight_device, value)
+    ).map_err(|err| format!("Failed to set backlight brightness via logind: {}", err))?;
+    Ok(())
+}
+
+/// Resolves the I2C device node carrying DDC/CI for an external monitor output,
+/// trying the AMD and Intel DRM layouts in turn. Returns `None` if neither matches,
+/// rather than panicking. Detection diagnostics only print to stderr when `verbose`
+/// is set, so callers that probe many outputs (`list`) can keep their table clean.
+fn try_get_i2c_dev_by_output(output: &str, verbose: bool) -> Option<String> {
+  // Find the DRM output directory
+  let output_path = Path::new("/sys/class/drm/").read_dir().ok()?.find(|d|
+   d.as_ref().unwrap().path().to_str().unwrap().ends_with(output)
+  ).map(|d| d.unwrap())?.path();
+
+  // Try AMD GPU structure first: check for i2c-N directories
+  if let Ok(entries) = output_path.read_dir() {
+    for entry in entries {
+      if let Ok(entry) = entry {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("i2c-") {
+          let device_path = format!("/dev/{}", name);
+          if verbose {
+            eprintln!("AMD GPU detected: Found I2C device {} for output {}", device_path, output);
+          }
+          return Some(device_path);
+        }
+      }
+    }
+  }
+
+  // Fallback: try direct ddc symlink
+  let ddc_symlink = output_path.join("ddc");
+  if ddc_symlink.exists() {
+    // AMD GPUs have a direct symlink to the i2c device
+    if let Ok(target) = std::fs::read_link(&ddc_symlink) {
+      // Extract i2c device number from the symlink target (e.g., "../../../i2c-7" -> "i2c-7")
+      if let Some(i2c_name) = target.file_name().and_then(|n| n.to_str()) {
+        let device_path = format!("/dev/{}", i2c_name);
+        if verbose {
+          eprintln!("AMD GPU detected: Found I2C device {} for output {} (via ddc symlink)", device_path, output);
+        }
+        return Some(device_path);
+      }
+    }
+  }
+
+  // Try Intel GPU structure: ddc/i2c-dev/
+  let intel_path = output_path.join("ddc").join("i2c-dev");
+  if intel_path.exists() {
+    if let Ok(mut entries) = intel_path.read_dir() {
+      if let Some(Ok(entry)) = entries.next() {
+        let dev_name = entry.file_name().to_string_lossy().to_string();
+        let device_path = format!("/dev/{}", dev_name);
+        if verbose {
+          eprintln!("Intel GPU detected: Found I2C device {} for output {}", device_path, output);
+        }
+        return Some(device_path);
+      }
+    }
+  }
+
+  None
+}
@@ -0,0 +1,113 @@
+/*
+Queries and parses the DDC/CI capabilities string, whose grammar looks like:
+
+    (prot(monitor)type(lcd)model(...)cmds(...)vcp(10 12 14(05 08 0B) 16 18 1A 60(0F 11 12) ...)mccs_ver(2.1))
+
+We only care about pulling the set of supported VCP feature codes out of the
+`vcp(...)` group; per-code allowed-value lists (e.g. `60(0F 11 12)`) are
+parsed past but discarded, since we just want to know which codes exist.
+*/
+
+use ddc::Ddc;
+
+use crate::retry::{with_retry, RetryConfig};
+
+pub fn get_capabilities_string(i2c_path: &str, retry: &RetryConfig) -> Result<String, String> {
+    let bytes = with_retry(retry, || {
+        let mut i2c_ddc = ddc_i2c::from_i2c_device(i2c_path).map_err(|err| err.to_string())?;
+        i2c_ddc.capabilities_string().map_err(|err| err.to_string())
+    })?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Extracts the list of supported VCP feature codes from a raw capabilities string.
+pub fn parse_vcp_codes(capabilities: &str) -> Vec<u8> {
+    let vcp_start = match capabilities.find("vcp(") {
+        Some(i) => i + "vcp(".len(),
+        None => return Vec::new(),
+    };
+    let inner = extract_balanced(&capabilities[vcp_start..]);
+
+    let mut codes = Vec::new();
+    let mut depth = 0;
+    let mut token = String::new();
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                push_code(&mut token, &mut codes);
+            }
+            c if depth == 0 => token.push(c),
+            _ => {}
+        }
+    }
+    push_code(&mut token, &mut codes);
+    codes
+}
+
+fn push_code(token: &mut String, codes: &mut Vec<u8>) {
+    if !token.is_empty() {
+        if let Ok(code) = u8::from_str_radix(token, 16) {
+            codes.push(code);
+        }
+        token.clear();
+    }
+}
+
+/// Returns the substring up to (not including) the paren that closes the
+/// currently-open group, given a string positioned just after its opening `(`.
+fn extract_balanced(s: &str) -> &str {
+    let mut depth = 1;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &s[..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    s
+}
+
+pub use crate::vcp::vcp_name;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flat_codes() {
+        assert_eq!(parse_vcp_codes("(prot(monitor)vcp(10 12 60)mccs_ver(2.1))"), vec![0x10, 0x12, 0x60]);
+    }
+
+    #[test]
+    fn parses_nested_allowed_value_groups() {
+        assert_eq!(
+            parse_vcp_codes("(prot(monitor)vcp(10 12 14(05 08 0B) 16 18 1A 60(0F 11 12))mccs_ver(2.1))"),
+            vec![0x10, 0x12, 0x14, 0x16, 0x18, 0x1A, 0x60]
+        );
+    }
+
+    #[test]
+    fn parses_trailing_grouped_code() {
+        // The last code in the vcp() group itself carries an allowed-value group.
+        assert_eq!(parse_vcp_codes("(vcp(10 60(01 02 03)))"), vec![0x10, 0x60]);
+    }
+
+    #[test]
+    fn missing_vcp_group_returns_empty() {
+        assert_eq!(parse_vcp_codes("(prot(monitor)type(lcd)mccs_ver(2.1))"), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn unbalanced_vcp_group_does_not_panic() {
+        // A truncated/malformed capabilities string shouldn't crash the parser;
+        // it should just read as far as it can.
+        assert_eq!(parse_vcp_codes("(prot(monitor)vcp(10 12"), vec![0x10, 0x12]);
+    }
+}
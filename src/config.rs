@@ -0,0 +1,52 @@
+/*
+Per-device configuration, keyed by the same device identifier the CLI prints
+when resolving a `Backend` (an I2C device name like "i2c-7", or a backlight
+device name like "intel_backlight"). Currently this only carries the
+`max_override` escape hatch for monitors that misreport their VCP 0x10
+maximum, but it's a natural place to grow other per-device overrides.
+*/
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct Config {
+    #[serde(default, rename = "device")]
+    pub devices: HashMap<String, DeviceConfig>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct DeviceConfig {
+    /// Overrides the monitor-reported VCP 0x10 maximum with this value before
+    /// any percentage math, for monitors that report a bogus max.
+    pub max_override: Option<u16>,
+}
+
+impl Config {
+    /// Loads the config file from `$XDG_CONFIG_HOME/ddcbacklight/config.toml`
+    /// (falling back to `~/.config/...`), or returns an empty config if it
+    /// doesn't exist.
+    pub fn load() -> Config {
+        let path = config_path();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                eprintln!("Failed to parse config file {}: {}", path.display(), err);
+                std::process::exit(1);
+            }),
+            Err(_) => Config::default(),
+        }
+    }
+
+    pub fn max_override_for(&self, device_key: &str) -> Option<u16> {
+        self.devices.get(device_key).and_then(|d| d.max_override)
+    }
+}
+
+fn config_path() -> PathBuf {
+    let config_home = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(std::env::var("HOME").unwrap()).join(".config"));
+    config_home.join("ddcbacklight").join("config.toml")
+}
@@ -2,127 +2,79 @@
 CLI app that controls monitor brightness using DDC/CI protocol.
 */
 
-use std::path::Path;
-
-use clap::{Command, Arg};
-use ddc_i2c::from_i2c_device;
-use ddc::Ddc;
 use mccs::Value;
 use lazy_static::lazy_static;
 // use lazycell::LazyCell;
 // use i3ipc::I3Connection;
 use swayipc::Connection;
 
+use clap::{Command, Arg};
+
+mod backend;
+mod capabilities;
+mod config;
+mod retry;
+mod vcp;
+use backend::Backend;
+use config::Config;
+use retry::RetryConfig;
 
-fn value_to_current_and_max(value: Value) -> (u16, u16) {
+pub(crate) fn value_to_current_and_max(value: Value) -> (u16, u16) {
     (value.sh as u16 * 256 + value.sl as u16, value.mh as u16 * 256 + value.ml as u16)
 }
 
-const BRIGHTNESS_VCP_CODE: u8 = 0x10;
+pub(crate) const BRIGHTNESS_VCP_CODE: u8 = 0x10;
 
 lazy_static! {
     static ref SWAYIPC: std::sync::Mutex<Connection> = std::sync::Mutex::new(Connection::new().unwrap());
-    static ref DBUS_SYSTEM: std::sync::Mutex<zbus::blocking::Connection> = std::sync::Mutex::new(zbus::blocking::Connection::system().unwrap());
 }
 
 fn get_active_output() -> String {
     SWAYIPC.lock().unwrap().get_outputs().unwrap().iter().find(|o| o.focused).unwrap().name.clone()
 }
 
-fn get_i2c_dev_by_output(output: &str) -> String {
-  // Embedded display port displays don't support DDC/CI protocol.
-  if output.starts_with("eDP") {
-    eprintln!("Trying to set brightness of an embedded display port monitor. Aborting.");
-    std::process::exit(1);
-  }
-
-  // Find the DRM output directory
-  let output_path = Path::new("/sys/class/drm/").read_dir().unwrap().find(|d|
-   d.as_ref().unwrap().path().to_str().unwrap().ends_with(output)
-  ).map(|d| d.unwrap());
-
-  if output_path.is_none() {
-    panic!("No such output: {}", output);
-  }
-
-  let output_path = output_path.unwrap().path();
-
-  // Try AMD GPU structure first: check for i2c-N directories
-  if let Ok(entries) = output_path.read_dir() {
-    for entry in entries {
-      if let Ok(entry) = entry {
-        let name = entry.file_name().to_string_lossy().to_string();
-        if name.starts_with("i2c-") {
-          let device_path = format!("/dev/{}", name);
-          eprintln!("AMD GPU detected: Found I2C device {} for output {}", device_path, output);
-          return device_path;
-        }
-      }
-    }
-  }
-
-  // Fallback: try direct ddc symlink
-  let ddc_symlink = output_path.join("ddc");
-  if ddc_symlink.exists() {
-    // AMD GPUs have a direct symlink to the i2c device
-    if let Ok(target) = std::fs::read_link(&ddc_symlink) {
-      // Extract i2c device number from the symlink target (e.g., "../../../i2c-7" -> "i2c-7")
-      if let Some(i2c_name) = target.file_name().and_then(|n| n.to_str()) {
-        let device_path = format!("/dev/{}", i2c_name);
-        eprintln!("AMD GPU detected: Found I2C device {} for output {} (via ddc symlink)", device_path, output);
-        return device_path;
-      }
-    }
-  }
-
-  // Try Intel GPU structure: ddc/i2c-dev/
-  let intel_path = output_path.join("ddc").join("i2c-dev");
-  if intel_path.exists() {
-    if let Ok(mut entries) = intel_path.read_dir() {
-      if let Some(Ok(entry)) = entries.next() {
-        let dev_name = entry.file_name().to_string_lossy().to_string();
-        let device_path = format!("/dev/{}", dev_name);
-        eprintln!("Intel GPU detected: Found I2C device {} for output {}", device_path, output);
-        return device_path;
-      }
-    }
-  }
-
-  panic!("Could not find I2C device for output: {}. Neither AMD nor Intel DDC structure found.", output);
+/// Generic VCP access and capabilities only make sense against a DDC/CI monitor;
+/// embedded eDP panels don't expose VCP codes.
+fn require_i2c_path(backend: &Backend) -> &str {
+    backend.i2c_path().unwrap_or_else(|| {
+        eprintln!("This command requires a DDC/CI-capable external monitor; eDP panels don't expose VCP codes.");
+        std::process::exit(1);
+    })
 }
 
-fn set_edp_brightness(backlight_device: &str, value: u16) {
-    // backlight device is something like "intel_backlight"
-    // systemd-logind gives a function to set brightness level for backlight devices.
-    // This doesn't require extra autentication or filesystem ACL for /sys/class/backlight devices as
-    // the high-privileged systemd-logind daemon can does that.
-    let dbus_system = DBUS_SYSTEM.lock().unwrap();
-    let _reply = dbus_system.call_method(
-        Some( "org.freedesktop.login1"),
-        "/org/freedesktop/login1/session/auto",
-        Some("org.freedesktop.login1.Session"),
-        "SetBrightness",
-        &("backlight", backlight_device, value)
-    ).unwrap();
+/// Unwraps a fallible DDC/CI operation, printing a clean error and exiting
+/// non-zero instead of panicking, so the tool is usable in scripts.
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    })
 }
 
-
-
-// impl Backend {
-//     fn set_brightness(&self, value: u16) {
-//         match self {
-//             Backend::Backlight(backlight_device) => set_edp_brightness(backlight_device, value),
-//             Backend::DdcI2c(i2c_path) => set_ddc_i2c_brightness(i2c_path, value),
-//         }
-//     }
-// }
-
-// enum Backend {
-//     /// Backlight refers to the /sys/class/backlight device. I.e. "intel_backlight".
-//     Backlight(String),
-//     /// DdcI2c refers to the I2C device that supports DDC/CI protocol. I.e. "/dev/i2c-10".
-//     DdcI2c(I2cDeviceDdc),
-// }
+/// Walks every sway output, resolves each one's backend (reusing the same AMD/Intel
+/// DDC detection and eDP backlight resolution as `-i`-less single-output commands),
+/// and prints a table of its current state. Outputs with no DDC/CI support are
+/// listed too, so a user can tell "not detected" apart from "didn't ask".
+fn list_outputs() {
+    let outputs = SWAYIPC.lock().unwrap().get_outputs().unwrap();
+    println!("{:<10} {:<20} {:<18} {:>8} {:>8}", "OUTPUT", "KIND", "DEVICE", "CURRENT", "MAX");
+    for output in outputs.iter() {
+        match Backend::try_for_output(&output.name, false) {
+            Some(backend) => {
+                let kind = match backend {
+                    Backend::Backlight(_) => "eDP (backlight)",
+                    Backend::DdcI2c(_) => "DDC/CI",
+                };
+                let device = backend.device_key();
+                match backend.try_get_brightness(None) {
+                    Some((current, max)) => println!("{:<10} {:<20} {:<18} {:>8} {:>8}", output.name, kind, device, current, max),
+                    None => println!("{:<10} {:<20} {:<18} {:>8} {:>8}", output.name, kind, device, "?", "?"),
+                }
+            },
+            None => println!("{:<10} {:<20} {:<18} {:>8} {:>8}", output.name, "no DDC/CI support", "-", "-", "-"),
+        }
+    }
+}
 
 fn main() {
     let matches = Command::new("monitor-brightness")
@@ -136,6 +88,27 @@ fn main() {
                 .required(false)
                 .help("Path to the I2C device")
         )
+        .arg(
+            Arg::new("max_override")
+                .long("max-override")
+                .value_parser(clap::value_parser!(u16))
+                .required(false)
+                .help("Override the monitor-reported maximum brightness (VCP 0x10 mh/ml), for monitors that misreport it")
+        )
+        .arg(
+            Arg::new("retries")
+                .long("retries")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("3")
+                .help("Number of times to retry a failed DDC/CI transaction")
+        )
+        .arg(
+            Arg::new("retry_delay_ms")
+                .long("retry-delay-ms")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("50")
+                .help("Delay between DDC/CI retries, in milliseconds")
+        )
         .subcommand(
             Command::new("get-brightness")
                 .about("Get current brightness value")
@@ -148,23 +121,80 @@ fn main() {
                         .required(true)
                         .help("Brightness value (0-100)")
                 )
+                .arg(
+                    Arg::new("duration")
+                        .long("duration")
+                        .value_parser(clap::value_parser!(u64))
+                        .required(false)
+                        .help("Ramp to the target brightness over this many milliseconds instead of jumping instantly")
+                )
+                .arg(
+                    Arg::new("steps")
+                        .long("steps")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("20")
+                        .help("Number of intermediate steps to use when ramping with --duration")
+                )
+        )
+        .subcommand(
+            Command::new("get-vcp")
+                .about("Get a raw VCP feature value")
+                .arg(
+                    Arg::new("code")
+                        .required(true)
+                        .help("VCP feature code in hex, e.g. 0x12 or 12")
+                )
+        )
+        .subcommand(
+            Command::new("set-vcp")
+                .about("Set a raw VCP feature value")
+                .arg(
+                    Arg::new("code")
+                        .required(true)
+                        .help("VCP feature code in hex, e.g. 0x12 or 12")
+                )
+                .arg(
+                    Arg::new("value")
+                        .required(true)
+                        .help("Value to write")
+                )
+        )
+        .subcommand(
+            Command::new("capabilities")
+                .about("Query and parse the monitor's MCCS capability string")
+        )
+        .subcommand(
+            Command::new("list")
+                .visible_alias("detect")
+                .about("List all outputs and their DDC/CI or eDP backlight state")
         )
         .get_matches();
 
+    if let Some(("list", _)) = matches.subcommand() {
+        list_outputs();
+        return;
+    }
+
     let i2c_path_maybe = matches.get_one::<String>("i2c_path");
 
-    let i2c_path = if let Some(i2c_path) = i2c_path_maybe {
-        i2c_path.to_string()
+    let backend = if let Some(i2c_path) = i2c_path_maybe {
+        Backend::DdcI2c(i2c_path.to_string())
     } else {
         let active_output = get_active_output();
-        get_i2c_dev_by_output(&active_output)
+        Backend::for_output(&active_output)
     };
 
-    let mut i2c_ddc = from_i2c_device(i2c_path).unwrap();
+    let config = Config::load();
+    let max_override = matches.get_one::<u16>("max_override").copied()
+        .or_else(|| config.max_override_for(&backend.device_key()));
+    let retry = RetryConfig {
+        retries: *matches.get_one::<u32>("retries").unwrap(),
+        delay: std::time::Duration::from_millis(*matches.get_one::<u64>("retry_delay_ms").unwrap()),
+    };
 
     match matches.subcommand() {
         Some(("get-brightness", _)) => {
-            let (current_value, max_value) = value_to_current_and_max(i2c_ddc.get_vcp_feature(BRIGHTNESS_VCP_CODE).unwrap());
+            let (current_value, max_value) = or_exit(backend.get_brightness(max_override, &retry));
 
             // Convert to percentage
             let percentage = (current_value as f32 / max_value as f32 * 100.0).round() as u16;
@@ -174,7 +204,7 @@ fn main() {
             let value_str = sub_matches.get_one::<String>("value").unwrap();
 
             // Get current brightness first
-            let (current_value, max_value) = value_to_current_and_max(i2c_ddc.get_vcp_feature(BRIGHTNESS_VCP_CODE).unwrap());
+            let (current_value, max_value) = or_exit(backend.get_brightness(max_override, &retry));
             let current_percentage = (current_value as f32 / max_value as f32 * 100.0).round() as i16;
 
             // Parse the value, handling relative changes
@@ -191,11 +221,41 @@ fn main() {
 
             // Convert percentage to absolute value
             let absolute_value = ((target_percentage as f32 / 100.0) * max_value as f32).round() as u16;
-            i2c_ddc.set_vcp_feature(BRIGHTNESS_VCP_CODE, absolute_value).unwrap();
+
+            match sub_matches.get_one::<u64>("duration") {
+                Some(duration_ms) => {
+                    let steps = *sub_matches.get_one::<u32>("steps").unwrap();
+                    let duration = std::time::Duration::from_millis(*duration_ms);
+                    or_exit(backend.ramp_brightness(current_value, absolute_value, duration, steps, &retry));
+                },
+                None => or_exit(backend.set_brightness(absolute_value, &retry)),
+            }
             println!("Brightness set to {}%", target_percentage);
         },
+        Some(("get-vcp", sub_matches)) => {
+            let code = vcp::parse_vcp_code(sub_matches.get_one::<String>("code").unwrap());
+            let i2c_path = require_i2c_path(&backend);
+            let (current, max) = value_to_current_and_max(or_exit(vcp::get_vcp(i2c_path, code, &retry)));
+            println!("VCP {:#04x} ({}): current={} max={}", code, vcp::vcp_name(code), current, max);
+        },
+        Some(("set-vcp", sub_matches)) => {
+            let code = vcp::parse_vcp_code(sub_matches.get_one::<String>("code").unwrap());
+            let value = vcp::parse_vcp_value(sub_matches.get_one::<String>("value").unwrap());
+            let i2c_path = require_i2c_path(&backend);
+            or_exit(vcp::set_vcp(i2c_path, code, value, &retry));
+            println!("VCP {:#04x} ({}) set to {}", code, vcp::vcp_name(code), value);
+        },
+        Some(("capabilities", _)) => {
+            let i2c_path = require_i2c_path(&backend);
+            let caps = or_exit(capabilities::get_capabilities_string(i2c_path, &retry));
+            let codes = capabilities::parse_vcp_codes(&caps);
+            println!("Supported VCP codes:");
+            for code in codes {
+                println!("  {:#04x} {}", code, capabilities::vcp_name(code));
+            }
+        },
         _ => {
-            eprintln!("Please specify either 'get-brightness' or 'set-brightness' command");
+            eprintln!("Please specify a command: 'get-brightness', 'set-brightness', 'get-vcp', 'set-vcp', 'capabilities', or 'list'");
             std::process::exit(1);
         }
     }
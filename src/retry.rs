@@ -0,0 +1,42 @@
+/*
+DDC/CI over I2C is notoriously unreliable: transfers intermittently NAK or
+return corrupt/short reads. This wraps a fallible operation (a VCP read or
+write) with a few retries, sleeping the DDC/CI minimum inter-message delay
+between attempts, instead of letting a single flaky transaction panic the
+whole program.
+*/
+
+use std::time::Duration;
+
+#[derive(Clone, Copy)]
+pub struct RetryConfig {
+    pub retries: u32,
+    pub delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig { retries: 3, delay: Duration::from_millis(50) }
+    }
+}
+
+/// Runs `attempt` up to `1 + config.retries` times, sleeping `config.delay` in
+/// between, returning the last error (stringified) if every attempt fails.
+pub fn with_retry<T, E: std::fmt::Display>(
+    config: &RetryConfig,
+    mut attempt: impl FnMut() -> Result<T, E>,
+) -> Result<T, String> {
+    let mut last_err = String::new();
+    for attempt_number in 0..=config.retries {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                last_err = err.to_string();
+                if attempt_number < config.retries {
+                    std::thread::sleep(config.delay);
+                }
+            }
+        }
+    }
+    Err(last_err)
+}
@@ -0,0 +1,98 @@
+/*
+Generic access to arbitrary VCP (Virtual Control Panel) features over DDC/CI,
+beyond the hardcoded brightness (0x10) control. This only applies to external
+DDC/CI monitors; embedded eDP panels don't expose VCP codes at all.
+*/
+
+use ddc::Ddc;
+use mccs::Value;
+
+use crate::retry::{with_retry, RetryConfig};
+
+// `get_vcp_feature` itself checks the VCP feature reply's opcode against the one
+// we requested and errors out on mismatch before returning a `Value` (which
+// carries only the mh/ml/sh/sl bytes, not the opcode) - a mismatched reply is
+// just another reason for `with_retry` to retry.
+pub fn get_vcp(i2c_path: &str, code: u8, retry: &RetryConfig) -> Result<Value, String> {
+    with_retry(retry, || {
+        let mut i2c_ddc = ddc_i2c::from_i2c_device(i2c_path).map_err(|err| err.to_string())?;
+        i2c_ddc.get_vcp_feature(code).map_err(|err| err.to_string())
+    })
+}
+
+pub fn set_vcp(i2c_path: &str, code: u8, value: u16, retry: &RetryConfig) -> Result<(), String> {
+    with_retry(retry, || {
+        let mut i2c_ddc = ddc_i2c::from_i2c_device(i2c_path).map_err(|err| err.to_string())?;
+        i2c_ddc.set_vcp_feature(code, value).map_err(|err| err.to_string())
+    })
+}
+
+/// Human-readable name for the VCP codes we know about. Monitors can support
+/// many more manufacturer-specific codes; unrecognized ones just print as "unknown".
+pub fn vcp_name(code: u8) -> &'static str {
+    match code {
+        0x02 => "new control value",
+        0x04 => "restore factory defaults",
+        0x05 => "restore factory brightness/contrast defaults",
+        0x08 => "restore color defaults",
+        0x0B => "color temperature increment",
+        0x0C => "color temperature request",
+        0x10 => "brightness",
+        0x12 => "contrast",
+        0x14 => "select color preset",
+        0x16 => "video gain (red)",
+        0x18 => "video gain (green)",
+        0x1A => "video gain (blue)",
+        0x60 => "input source",
+        0x62 => "audio volume",
+        0xAC => "horizontal frequency",
+        0xAE => "vertical frequency",
+        0xB2 => "flat panel sub-pixel layout",
+        0xB6 => "display technology type",
+        0xC6 => "application enable key",
+        0xC8 => "display controller type",
+        0xC9 => "display firmware level",
+        0xD6 => "power mode",
+        0xDF => "VCP version",
+        _ => "unknown",
+    }
+}
+
+/// Parses a VCP feature code given on the command line, accepting both "0x12"
+/// and bare hex like "12".
+pub fn parse_vcp_code(s: &str) -> u8 {
+    let trimmed = s.trim_start_matches("0x").trim_start_matches("0X");
+    u8::from_str_radix(trimmed, 16).unwrap_or_else(|_| {
+        eprintln!("Invalid VCP code: {}", s);
+        std::process::exit(1);
+    })
+}
+
+/// Parses a VCP feature value given on the command line, exiting cleanly on
+/// bad input instead of panicking.
+pub fn parse_vcp_value(s: &str) -> u16 {
+    s.parse::<u16>().unwrap_or_else(|_| {
+        eprintln!("Invalid VCP value: {}", s);
+        std::process::exit(1);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_0x_prefixed_code() {
+        assert_eq!(parse_vcp_code("0x12"), 0x12);
+    }
+
+    #[test]
+    fn parses_bare_hex_code() {
+        assert_eq!(parse_vcp_code("60"), 0x60);
+    }
+
+    #[test]
+    fn parses_vcp_value() {
+        assert_eq!(parse_vcp_value("42"), 42);
+    }
+}